@@ -19,18 +19,24 @@
 #![allow(box_pointers, fat_ptr_transmutes, missing_copy_implementations,
          missing_debug_implementations)]
 
-#[cfg(target_os = "windows")]
+#[cfg(windows)]
 mod windows;
-#[cfg(target_os = "windows")]
+#[cfg(windows)]
 use windows::MmapInner;
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(unix)]
 mod posix;
-#[cfg(not(target_os = "windows"))]
+#[cfg(unix)]
 use posix::MmapInner;
 
+#[cfg(not(any(unix, windows)))]
+mod stub;
+#[cfg(not(any(unix, windows)))]
+use stub::MmapInner;
+
 use std::{fs, io};
 use std::borrow::{Borrow, BorrowMut};
+use std::fs::File;
 use std::ops::{
     Deref, DerefMut,
     Index, IndexMut,
@@ -78,27 +84,155 @@ impl Protection {
     }
 }
 
+/// A memory access pattern hint, passed to `Mmap::advise`/`MmapMut::advise`.
+///
+/// These correspond to the `madvise(2)` flags on POSIX systems. On Windows, only `WillNeed` has
+/// an effect (via `PrefetchVirtualMemory`); the rest are accepted but are no-ops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Advice {
+
+    /// No special treatment. This is the default behavior for newly mapped pages.
+    Normal,
+
+    /// Pages will be accessed in a random order, so aggressive read-ahead is unlikely to help.
+    Random,
+
+    /// Pages will be accessed sequentially, so the kernel may read ahead more aggressively and
+    /// free pages sooner after they are accessed.
+    Sequential,
+
+    /// The range will be accessed in the near future; the kernel may begin reading it into
+    /// memory ahead of time.
+    WillNeed,
+
+    /// The range will not be accessed in the near future, allowing the kernel to free the
+    /// underlying pages.
+    DontNeed,
+}
+
+/// A builder for configuring and creating a memory map.
+///
+/// `MmapOptions` allows a caller to map a sub-region of a file, rather than the whole thing, by
+/// setting an `offset` and a `len`. The offset is rounded down to a multiple of the system page
+/// size before the underlying mapping is made, but the returned `Mmap` still begins at the exact
+/// byte the caller asked for.
+///
+/// ```
+/// use memory_map::{MmapOptions, Protection};
+///
+/// let mmap = MmapOptions::new().offset(4096).len(4096).map("README.md", Protection::Read);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MmapOptions {
+    offset: usize,
+    len: Option<usize>,
+}
+
+impl MmapOptions {
+
+    /// Creates a new `MmapOptions` with the default settings: an offset of `0`, and a length
+    /// covering the entire file or anonymous region being mapped.
+    pub fn new() -> MmapOptions {
+        MmapOptions { offset: 0, len: None }
+    }
+
+    /// Sets the offset, in bytes, into the file at which the memory map begins.
+    ///
+    /// The offset does not need to be aligned to the system page size; alignment is handled
+    /// internally.
+    pub fn offset(&mut self, offset: usize) -> &mut MmapOptions {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the length, in bytes, of the memory map.
+    ///
+    /// If unset, a file-backed mapping created with `map` covers from `offset` to the end of the
+    /// file.
+    pub fn len(&mut self, len: usize) -> &mut MmapOptions {
+        self.len = Some(len);
+        self
+    }
+
+    /// Creates a read-only file-backed memory map as configured.
+    pub fn map<P>(&self, path: P, prot: Protection) -> io::Result<Mmap> where P: AsRef<Path> {
+        MmapInner::open(path.as_ref(), self.offset, self.len, prot).map(|inner| Mmap { inner: inner })
+    }
+
+    /// Creates a read-only anonymous memory map as configured.
+    ///
+    /// The `offset` set on the builder, if any, is ignored; anonymous maps have no underlying
+    /// file to seek into.
+    pub fn map_anon(&self, len: usize, prot: Protection) -> io::Result<Mmap> {
+        MmapInner::anonymous(len, prot).map(|inner| Mmap { inner: inner })
+    }
+
+    /// Creates a writable file-backed memory map as configured.
+    ///
+    /// `prot` must be writable; `Protection::Read` is rejected, since the resulting pages would
+    /// not actually support the writes `MmapMut`'s type promises.
+    pub fn map_mut<P>(&self, path: P, prot: Protection) -> io::Result<MmapMut> where P: AsRef<Path> {
+        try!(require_writable(prot));
+        MmapInner::open(path.as_ref(), self.offset, self.len, prot).map(|inner| MmapMut { inner: inner })
+    }
+
+    /// Creates a writable anonymous memory map as configured.
+    ///
+    /// The `offset` set on the builder, if any, is ignored; anonymous maps have no underlying
+    /// file to seek into. `prot` must be writable; `Protection::Read` is rejected.
+    pub fn map_anon_mut(&self, len: usize, prot: Protection) -> io::Result<MmapMut> {
+        try!(require_writable(prot));
+        MmapInner::anonymous(len, prot).map(|inner| MmapMut { inner: inner })
+    }
+
+    /// Creates a read-only memory map of an already-open file, as configured.
+    ///
+    /// The file's length is read from its metadata rather than by reopening its path, so this
+    /// works with files that have no path (e.g. unlinked temp files or inherited descriptors).
+    pub fn map_file(&self, file: &File, prot: Protection) -> io::Result<Mmap> {
+        MmapInner::from_file(file, self.offset, self.len, prot).map(|inner| Mmap { inner: inner })
+    }
+
+    /// Creates a writable memory map of an already-open file, as configured.
+    ///
+    /// `prot` must be writable; `Protection::Read` is rejected.
+    pub fn map_file_mut(&self, file: &File, prot: Protection) -> io::Result<MmapMut> {
+        try!(require_writable(prot));
+        MmapInner::from_file(file, self.offset, self.len, prot).map(|inner| MmapMut { inner: inner })
+    }
+}
+
+/// Returns an error unless `prot` is writable.
+///
+/// Used to guard every `MmapMut` constructor: a `Protection::Read` mapping is backed by
+/// `PROT_READ`-only pages, so letting it through would let `DerefMut`/`IndexMut` segfault on the
+/// very first write.
+fn require_writable(prot: Protection) -> io::Result<()> {
+    if prot.write() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                            "MmapMut requires a writable Protection"))
+    }
+}
+
 /// A memory-mapped buffer.
 ///
-/// A file-backed `Mmap` buffer may be used to read or write data to a file. Use `Mmap::open(..)` to
+/// A file-backed `Mmap` buffer may be used to read data from a file. Use `Mmap::open(..)` to
 /// create a file-backed memory map. An anonymous `Mmap` buffer may be used any place that an
 /// in-memory byte buffer is needed, and gives the added features of a memory map. Use
 /// `Mmap::anonymous(..)` to create an anonymous memory map.
 ///
-/// Changes written to a memory-mapped file are not guaranteed to be durable until the memory map is
-/// flushed, or it is dropped.
+/// `Mmap` only exposes read access to the mapped bytes; the underlying pages may still have been
+/// mapped writable (for example via `Protection::ReadWrite`), but writing through `Mmap` is not
+/// possible at the type level. Call `make_mut` to obtain a [`MmapMut`](struct.MmapMut.html),
+/// which adjusts the page protection in place if necessary.
 ///
 /// ```
-/// #[allow(dead_code)]
-/// use std::io::Write;
 /// use memory_map::{Mmap, Protection};
 ///
 /// let file_mmap = Mmap::open("README.md", Protection::Read).unwrap();
 /// assert_eq!(b"# Memory Map", &file_mmap[0..12]);
-///
-/// let mut anon_mmap = Mmap::anonymous(4096, Protection::ReadWrite).unwrap();
-/// (&mut *anon_mmap).write(b"foo").unwrap();
-/// assert_eq!(b"foo\0\0", &anon_mmap[0..5]);
 /// ```
 
 pub struct Mmap {
@@ -109,12 +243,166 @@ impl Mmap {
 
     /// Opens a file-backed memory map.
     pub fn open<P>(path: P, prot: Protection) -> io::Result<Mmap> where P: AsRef<Path> {
-        MmapInner::open(path, prot).map(|inner| Mmap { inner: inner })
+        MmapOptions::new().map(path, prot)
     }
 
     /// Opens an anonymous memory map.
     pub fn anonymous(len: usize, prot: Protection) -> io::Result<Mmap> {
-        MmapInner::anonymous(len, prot).map(|inner| Mmap { inner: inner })
+        MmapOptions::new().map_anon(len, prot)
+    }
+
+    /// Maps an already-open file.
+    ///
+    /// Unlike `open`, this takes a borrowed `File` directly, so it works with files opened with
+    /// flags `open` does not expose, unlinked temp files, or descriptors received from elsewhere.
+    /// The file's length is read from its metadata rather than by reopening its path.
+    pub fn map(file: &File, prot: Protection) -> io::Result<Mmap> {
+        MmapOptions::new().map_file(file, prot)
+    }
+
+    /// Returns the length of the memory map.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Advises the kernel of the access pattern intended for the whole mapping.
+    pub fn advise(&self, advice: Advice) -> io::Result<()> {
+        self.inner.advise(advice)
+    }
+
+    /// Advises the kernel of the access pattern intended for the given byte range.
+    ///
+    /// `offset` is rounded down to a page boundary, with `len` extended to compensate. An
+    /// `offset`/`len` that falls outside the mapping is an error.
+    pub fn advise_range(&self, offset: usize, len: usize, advice: Advice) -> io::Result<()> {
+        self.inner.advise_range(offset, len, advice)
+    }
+
+    /// Locks the memory map into physical memory, preventing it from being paged to swap.
+    ///
+    /// This is useful for latency-sensitive or secure workloads that cannot tolerate a page
+    /// fault, or that must guarantee sensitive data is never written to swap. Failure (for
+    /// example `ENOMEM` from `RLIMIT_MEMLOCK` exhaustion, or `EPERM`) is returned as an error.
+    pub fn lock(&mut self) -> io::Result<()> {
+        self.inner.lock()
+    }
+
+    /// Unlocks the memory map, allowing its pages to be paged out again.
+    pub fn unlock(&mut self) -> io::Result<()> {
+        self.inner.unlock()
+    }
+
+    /// Transitions the memory map to be writable, changing the underlying page protection in
+    /// place rather than creating a new mapping.
+    ///
+    /// This calls `mprotect`/`VirtualProtect` under the hood, so it can fail (for example if the
+    /// mapping was created from a file opened without write permission).
+    pub fn make_mut(self) -> io::Result<MmapMut> {
+        let mut inner = self.inner;
+        try!(inner.mprotect(Protection::ReadWrite));
+        Ok(MmapMut { inner: inner })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &*self.inner
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        &*self
+    }
+}
+
+impl Borrow<[u8]> for Mmap {
+    fn borrow(&self) -> &[u8] {
+        &*self
+    }
+}
+
+impl Index<usize> for Mmap {
+    type Output = u8;
+
+    fn index(&self, index: usize) -> &u8 {
+        &(*self.inner)[index]
+    }
+}
+
+impl Index<Range<usize>> for Mmap {
+    type Output = [u8];
+
+    fn index(&self, index: Range<usize>) -> &[u8] {
+        Index::index(&**self, index)
+    }
+}
+
+impl Index<RangeTo<usize>> for Mmap {
+    type Output = [u8];
+
+    fn index(&self, index: RangeTo<usize>) -> &[u8] {
+        Index::index(&**self, index)
+    }
+}
+
+impl Index<RangeFrom<usize>> for Mmap {
+    type Output = [u8];
+
+    fn index(&self, index: RangeFrom<usize>) -> &[u8] {
+        Index::index(&**self, index)
+    }
+}
+
+impl Index<RangeFull> for Mmap {
+    type Output = [u8];
+
+    fn index(&self, _index: RangeFull) -> &[u8] {
+        self
+    }
+}
+
+/// A writable memory-mapped buffer.
+///
+/// `MmapMut` is the read-write counterpart to [`Mmap`](struct.Mmap.html): it additionally
+/// implements `DerefMut`/`IndexMut`, and is the only one of the two types that can be flushed to
+/// disk. Use `MmapMut::open(..)` or `MmapMut::anonymous(..)` to create one directly, or
+/// `Mmap::make_mut` to upgrade an existing read-only map.
+///
+/// ```
+/// #[allow(dead_code)]
+/// use std::io::Write;
+/// use memory_map::{MmapMut, Protection};
+///
+/// let mut anon_mmap = MmapMut::anonymous(4096, Protection::ReadWrite).unwrap();
+/// (&mut *anon_mmap).write(b"foo").unwrap();
+/// assert_eq!(b"foo\0\0", &anon_mmap[0..5]);
+/// ```
+pub struct MmapMut {
+    inner: MmapInner
+}
+
+impl MmapMut {
+
+    /// Opens a file-backed, writable memory map.
+    pub fn open<P>(path: P, prot: Protection) -> io::Result<MmapMut> where P: AsRef<Path> {
+        MmapOptions::new().map_mut(path, prot)
+    }
+
+    /// Opens an anonymous, writable memory map.
+    pub fn anonymous(len: usize, prot: Protection) -> io::Result<MmapMut> {
+        MmapOptions::new().map_anon_mut(len, prot)
+    }
+
+    /// Maps an already-open file for writing.
+    ///
+    /// Unlike `open`, this takes a borrowed `File` directly, so it works with files opened with
+    /// flags `open` does not expose, unlinked temp files, or descriptors received from elsewhere.
+    /// The file's length is read from its metadata rather than by reopening its path.
+    pub fn map(file: &File, prot: Protection) -> io::Result<MmapMut> {
+        MmapOptions::new().map_file_mut(file, prot)
     }
 
     /// Flushes outstanding memory map modifications to disk.
@@ -135,13 +423,63 @@ impl Mmap {
         self.inner.flush_async()
     }
 
+    /// Flushes outstanding memory map modifications in the given byte range to disk.
+    ///
+    /// Only the pages covering `offset..offset + len` are synced, rather than the whole mapping,
+    /// which is cheaper when only a small region has been modified. `offset` is rounded down to
+    /// a page boundary, with `len` extended to compensate.
+    pub fn flush_range(&mut self, offset: usize, len: usize) -> io::Result<()> {
+        self.inner.flush_range(offset, len)
+    }
+
+    /// Asynchronously flushes outstanding memory map modifications in the given byte range to
+    /// disk.
+    pub fn flush_async_range(&mut self, offset: usize, len: usize) -> io::Result<()> {
+        self.inner.flush_async_range(offset, len)
+    }
+
     /// Returns the length of the memory map.
     pub fn len(&self) -> usize {
         self.inner.len()
     }
+
+    /// Advises the kernel of the access pattern intended for the whole mapping.
+    pub fn advise(&self, advice: Advice) -> io::Result<()> {
+        self.inner.advise(advice)
+    }
+
+    /// Advises the kernel of the access pattern intended for the given byte range.
+    ///
+    /// `offset` is rounded down to a page boundary, with `len` extended to compensate. An
+    /// `offset`/`len` that falls outside the mapping is an error.
+    pub fn advise_range(&self, offset: usize, len: usize, advice: Advice) -> io::Result<()> {
+        self.inner.advise_range(offset, len, advice)
+    }
+
+    /// Locks the memory map into physical memory, preventing it from being paged to swap.
+    ///
+    /// This is useful for latency-sensitive or secure workloads that cannot tolerate a page
+    /// fault, or that must guarantee sensitive data is never written to swap. Failure (for
+    /// example `ENOMEM` from `RLIMIT_MEMLOCK` exhaustion, or `EPERM`) is returned as an error.
+    pub fn lock(&mut self) -> io::Result<()> {
+        self.inner.lock()
+    }
+
+    /// Unlocks the memory map, allowing its pages to be paged out again.
+    pub fn unlock(&mut self) -> io::Result<()> {
+        self.inner.unlock()
+    }
+
+    /// Transitions the memory map to be read-only, changing the underlying page protection in
+    /// place rather than creating a new mapping.
+    pub fn make_read_only(self) -> io::Result<Mmap> {
+        let mut inner = self.inner;
+        try!(inner.mprotect(Protection::Read));
+        Ok(Mmap { inner: inner })
+    }
 }
 
-impl Deref for Mmap {
+impl Deref for MmapMut {
     type Target = [u8];
 
     fn deref(&self) -> &[u8] {
@@ -149,37 +487,37 @@ impl Deref for Mmap {
     }
 }
 
-impl DerefMut for Mmap {
+impl DerefMut for MmapMut {
     fn deref_mut(&mut self) -> &mut [u8] {
         &mut *self.inner
     }
 }
 
-impl AsRef<[u8]> for Mmap {
+impl AsRef<[u8]> for MmapMut {
     fn as_ref(&self) -> &[u8] {
         &*self
     }
 }
 
-impl AsMut<[u8]> for Mmap {
+impl AsMut<[u8]> for MmapMut {
     fn as_mut(&mut self) -> &mut [u8] {
         &mut *self
     }
 }
 
-impl Borrow<[u8]> for Mmap {
+impl Borrow<[u8]> for MmapMut {
     fn borrow(&self) -> &[u8] {
         &*self
     }
 }
 
-impl BorrowMut<[u8]> for Mmap {
+impl BorrowMut<[u8]> for MmapMut {
     fn borrow_mut(&mut self) -> &mut [u8] {
         &mut *self
     }
 }
 
-impl Index<usize> for Mmap {
+impl Index<usize> for MmapMut {
     type Output = u8;
 
     fn index(&self, index: usize) -> &u8 {
@@ -187,13 +525,13 @@ impl Index<usize> for Mmap {
     }
 }
 
-impl IndexMut<usize> for Mmap {
+impl IndexMut<usize> for MmapMut {
     fn index_mut(&mut self, index: usize) -> &mut u8 {
         &mut (*self.inner)[index]
     }
 }
 
-impl Index<Range<usize>> for Mmap {
+impl Index<Range<usize>> for MmapMut {
     type Output = [u8];
 
     fn index(&self, index: Range<usize>) -> &[u8] {
@@ -201,7 +539,7 @@ impl Index<Range<usize>> for Mmap {
     }
 }
 
-impl Index<RangeTo<usize>> for Mmap {
+impl Index<RangeTo<usize>> for MmapMut {
     type Output = [u8];
 
     fn index(&self, index: RangeTo<usize>) -> &[u8] {
@@ -209,7 +547,7 @@ impl Index<RangeTo<usize>> for Mmap {
     }
 }
 
-impl Index<RangeFrom<usize>> for Mmap {
+impl Index<RangeFrom<usize>> for MmapMut {
     type Output = [u8];
 
     fn index(&self, index: RangeFrom<usize>) -> &[u8] {
@@ -217,7 +555,7 @@ impl Index<RangeFrom<usize>> for Mmap {
     }
 }
 
-impl Index<RangeFull> for Mmap {
+impl Index<RangeFull> for MmapMut {
     type Output = [u8];
 
     fn index(&self, _index: RangeFull) -> &[u8] {
@@ -225,25 +563,25 @@ impl Index<RangeFull> for Mmap {
     }
 }
 
-impl IndexMut<Range<usize>> for Mmap {
+impl IndexMut<Range<usize>> for MmapMut {
     fn index_mut(&mut self, index: Range<usize>) -> &mut [u8] {
         IndexMut::index_mut(&mut **self, index)
     }
 }
 
-impl IndexMut<RangeTo<usize>> for Mmap {
+impl IndexMut<RangeTo<usize>> for MmapMut {
     fn index_mut(&mut self, index: RangeTo<usize>) -> &mut [u8] {
         IndexMut::index_mut(&mut **self, index)
     }
 }
 
-impl IndexMut<RangeFrom<usize>> for Mmap {
+impl IndexMut<RangeFrom<usize>> for MmapMut {
     fn index_mut(&mut self, index: RangeFrom<usize>) -> &mut [u8] {
         IndexMut::index_mut(&mut **self, index)
     }
 }
 
-impl IndexMut<RangeFull> for Mmap {
+impl IndexMut<RangeFull> for MmapMut {
     fn index_mut(&mut self, _index: RangeFull) -> &mut [u8] {
         self
     }
@@ -272,7 +610,7 @@ mod test {
                         .open(&path).unwrap()
                         .set_len(expected_len as u64).unwrap();
 
-        let mut mmap = Mmap::open(path, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::open(path, Protection::ReadWrite).unwrap();
         let len = mmap.len();
         assert_eq!(expected_len, len);
 
@@ -307,7 +645,7 @@ mod test {
     #[test]
     fn map_anon() {
         let expected_len = 128;
-        let mut mmap = Mmap::anonymous(expected_len, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::anonymous(expected_len, Protection::ReadWrite).unwrap();
         let len = mmap.len();
         assert_eq!(expected_len, len);
 
@@ -327,7 +665,7 @@ mod test {
     #[test]
     fn anonymous_overflow() {
         let expected_len = 128;
-        let mut mmap = Mmap::anonymous(expected_len, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::anonymous(expected_len, Protection::ReadWrite).unwrap();
         let len = mmap.len();
         assert_eq!(expected_len, len);
 
@@ -365,7 +703,7 @@ mod test {
         let write = b"abc123";
         let mut read = [0u8; 6];
 
-        let mut mmap = Mmap::open(&path, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::open(&path, Protection::ReadWrite).unwrap();
         let _ = (&mut mmap[..]).write(write).unwrap();
         mmap.flush().unwrap();
 
@@ -389,7 +727,7 @@ mod test {
         let incr = (0..EXPECTED_LENGTH + 1).map(|n| n as u8).collect::<Vec<_>>();
         let expected = (0..EXPECTED_LENGTH).map(|n| n as u8).collect::<Vec<_>>();
 
-        let mut mmap = Mmap::open(&path, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::open(&path, Protection::ReadWrite).unwrap();
 
         match (&mut mmap[..]).write(&incr[..]) {
             Ok(size) => assert_eq!(EXPECTED_LENGTH, size),
@@ -420,7 +758,7 @@ mod test {
         let write = b"abc123";
         let mut read = [0u8; 6];
 
-        let mut mmap = Mmap::open(&path, Protection::ReadCopy).unwrap();
+        let mut mmap = MmapMut::open(&path, Protection::ReadCopy).unwrap();
         let _ = (&mut mmap[..]).write(write).unwrap();
         mmap.flush().unwrap();
 
@@ -440,14 +778,132 @@ mod test {
 
     #[test]
     fn index() {
-        let mut mmap = Mmap::anonymous(128, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::anonymous(128, Protection::ReadWrite).unwrap();
+        mmap[0] = 42;
+        assert_eq!(42, mmap[0]);
+    }
+
+    #[test]
+    fn map_open_file() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+
+        let file = fs::OpenOptions::new()
+                                   .read(true)
+                                   .write(true)
+                                   .create(true)
+                                   .open(&path).unwrap();
+        file.set_len(128).unwrap();
+
+        let mut mmap = MmapMut::map(&file, Protection::ReadWrite).unwrap();
+        mmap[0] = 42;
+        mmap.flush().unwrap();
+
+        let mmap = Mmap::map(&file, Protection::Read).unwrap();
+        assert_eq!(42, mmap[0]);
+    }
+
+    #[test]
+    fn flush_range() {
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+
+        let mut file = fs::OpenOptions::new()
+                                       .read(true)
+                                       .write(true)
+                                       .create(true)
+                                       .open(&path).unwrap();
+        file.set_len(128).unwrap();
+
+        let mut mmap = MmapMut::open(&path, Protection::ReadWrite).unwrap();
+        let _ = (&mut mmap[..]).write(b"abc123").unwrap();
+        mmap.flush_range(0, 6).unwrap();
+        mmap.flush_async_range(6, 10).unwrap();
+
+        assert!(mmap.flush_range(0, 129).is_err());
+
+        let mut read = [0u8; 6];
+        let _ = file.read(&mut read).unwrap();
+        assert_eq!(b"abc123", &read);
+    }
+
+    #[test]
+    fn advise() {
+        let mmap = Mmap::anonymous(4096, Protection::Read).unwrap();
+        mmap.advise(Advice::Sequential).unwrap();
+        mmap.advise_range(0, 4096, Advice::WillNeed).unwrap();
+        assert!(mmap.advise_range(0, 4097, Advice::Normal).is_err());
+    }
+
+    #[test]
+    fn lock_unlock() {
+        let mut mmap = Mmap::anonymous(4096, Protection::Read).unwrap();
+        mmap.lock().unwrap();
+        mmap.unlock().unwrap();
+    }
+
+    #[test]
+    fn map_offset_window() {
+        // Content spans several pages worth of bytes, with a distinct, checkable value at every
+        // offset, so that a window into the middle can be verified byte-for-byte.
+        let len = 4 * 4096 + 100;
+        let content = (0..len).map(|n| (n % 256) as u8).collect::<Vec<_>>();
+
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+        fs::OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .open(&path).unwrap()
+                        .write_all(&content).unwrap();
+
+        // A small window that starts mid-page, so the internal page-aligned mapping has to trim
+        // leading bytes the caller didn't ask for.
+        let offset = 100;
+        let window_len = 50;
+        let mmap = MmapOptions::new().offset(offset).len(window_len).map(&path, Protection::Read).unwrap();
+        assert_eq!(window_len, mmap.len());
+        assert_eq!(&content[offset..offset + window_len], &*mmap);
+
+        // A window that starts mid-page and spans across a page boundary, exercising the same
+        // trimming with more than one page backing the mapping.
+        let offset = 4096 + 123;
+        let window_len = 8192;
+        let mmap = MmapOptions::new().offset(offset).len(window_len).map(&path, Protection::Read).unwrap();
+        assert_eq!(window_len, mmap.len());
+        assert_eq!(&content[offset..offset + window_len], &*mmap);
+    }
+
+    #[test]
+    fn mmap_mut_rejects_read_protection() {
+        assert!(MmapMut::anonymous(128, Protection::Read).is_err());
+
+        let tempdir = tempdir::TempDir::new("mmap").unwrap();
+        let path = tempdir.path().join("mmap");
+        fs::OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .open(&path).unwrap()
+                        .set_len(128).unwrap();
+        assert!(MmapMut::open(&path, Protection::Read).is_err());
+
+        let file = fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        assert!(MmapMut::map(&file, Protection::Read).is_err());
+    }
+
+    #[test]
+    fn make_mut() {
+        let mut mmap = Mmap::anonymous(128, Protection::Read).unwrap().make_mut().unwrap();
         mmap[0] = 42;
         assert_eq!(42, mmap[0]);
+
+        let mmap = mmap.make_read_only().unwrap();
+        assert_eq!(42, mmap[0]);
     }
 
     #[test]
     fn send() {
-        let mut mmap = Mmap::anonymous(128, Protection::ReadWrite).unwrap();
+        let mut mmap = MmapMut::anonymous(128, Protection::ReadWrite).unwrap();
         let _ = (&mut mmap[..]).write(b"foobar").unwrap();
         let _ = thread::spawn(move || {
             mmap.flush().unwrap();
@@ -0,0 +1,233 @@
+#![allow(unsafe_code)]
+
+extern crate libc;
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use {Advice, Protection};
+
+impl Advice {
+    fn as_madvise(self) -> libc::c_int {
+        match self {
+            Advice::Normal => libc::MADV_NORMAL,
+            Advice::Random => libc::MADV_RANDOM,
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::DontNeed => libc::MADV_DONTNEED,
+        }
+    }
+}
+
+impl Protection {
+    fn as_prot(self) -> libc::c_int {
+        match self {
+            Protection::Read => libc::PROT_READ,
+            Protection::ReadWrite | Protection::ReadCopy => libc::PROT_READ | libc::PROT_WRITE,
+        }
+    }
+
+    fn as_flag(self) -> libc::c_int {
+        match self {
+            Protection::Read | Protection::ReadWrite => libc::MAP_SHARED,
+            Protection::ReadCopy => libc::MAP_PRIVATE,
+        }
+    }
+}
+
+/// Returns the size of a page in bytes, as reported by the system.
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+pub struct MmapInner {
+    ptr: *mut libc::c_void,
+    len: usize,
+
+    /// The number of bytes between `ptr` and the byte the caller actually
+    /// asked to see. `ptr` always points at an `offset` that has been
+    /// rounded down to a page boundary, so this is `offset - aligned_offset`.
+    offset_adjustment: usize,
+}
+
+impl MmapInner {
+    /// Opens a file-backed memory map, starting `offset` bytes into the file and extending for
+    /// `len` bytes (or to the end of the file if `len` is `None`).
+    pub fn open(path: &Path, offset: usize, len: Option<usize>, prot: Protection) -> Result<MmapInner> {
+        let file = try!(prot.as_open_options().open(path));
+        MmapInner::from_file(&file, offset, len, prot)
+    }
+
+    /// Maps a region of an already-open file.
+    ///
+    /// The file's length is queried from its metadata rather than by reopening the path, so this
+    /// works with files that have no path at all (e.g. unlinked temp files or file descriptors
+    /// received from elsewhere).
+    pub fn from_file(file: &File, offset: usize, len: Option<usize>, prot: Protection) -> Result<MmapInner> {
+        let file_len = try!(file.metadata()).len() as usize;
+
+        let len = match len {
+            Some(len) => len,
+            None => try!(file_len.checked_sub(offset).ok_or_else(|| {
+                Error::new(ErrorKind::InvalidInput, "memory map offset is out of bounds")
+            })),
+        };
+
+        if offset.checked_add(len).map_or(true, |end| end > file_len) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                   "memory map offset and length are out of bounds of the file"));
+        }
+
+        unsafe { MmapInner::map(file.as_raw_fd(), offset, len, prot) }
+    }
+
+    /// Opens an anonymous memory map.
+    pub fn anonymous(len: usize, prot: Protection) -> Result<MmapInner> {
+        unsafe { MmapInner::map(-1, 0, len, prot) }
+    }
+
+    unsafe fn map(fd: libc::c_int, offset: usize, len: usize, prot: Protection) -> Result<MmapInner> {
+        let alignment = offset % page_size();
+        let aligned_offset = offset - alignment;
+        let aligned_len = len + alignment;
+
+        if aligned_len == 0 {
+            // A zero-length mapping is not allowed, but it's also not useful, so treat it as an
+            // error rather than mapping a single page that nobody asked for.
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map must have a non-zero length"));
+        }
+
+        let flag = if fd == -1 { prot.as_flag() | libc::MAP_ANON } else { prot.as_flag() };
+
+        let ptr = libc::mmap(ptr::null_mut(),
+                              aligned_len,
+                              prot.as_prot(),
+                              flag,
+                              fd,
+                              aligned_offset as libc::off_t);
+
+        if ptr == libc::MAP_FAILED {
+            Err(Error::last_os_error())
+        } else {
+            Ok(MmapInner {
+                ptr: ptr,
+                len: aligned_len,
+                offset_adjustment: alignment,
+            })
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        let result = unsafe { libc::msync(self.ptr, self.len, libc::MS_SYNC) };
+        if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    pub fn flush_async(&mut self) -> Result<()> {
+        let result = unsafe { libc::msync(self.ptr, self.len, libc::MS_ASYNC) };
+        if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    pub fn flush_range(&mut self, offset: usize, len: usize) -> Result<()> {
+        self.msync_range(offset, len, libc::MS_SYNC)
+    }
+
+    pub fn flush_async_range(&mut self, offset: usize, len: usize) -> Result<()> {
+        self.msync_range(offset, len, libc::MS_ASYNC)
+    }
+
+    fn msync_range(&mut self, offset: usize, len: usize, flags: libc::c_int) -> Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map flush range is out of bounds"));
+        }
+
+        unsafe {
+            let ptr = (self.ptr as *const u8).offset((self.offset_adjustment + offset) as isize);
+            let alignment = ptr as usize % page_size();
+            let aligned_ptr = ptr.offset(-(alignment as isize));
+            let aligned_len = len + alignment;
+
+            let result = libc::msync(aligned_ptr as *mut libc::c_void, aligned_len, flags);
+            if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len - self.offset_adjustment
+    }
+
+    /// Changes the protection of the full mapping in place.
+    pub fn mprotect(&mut self, prot: Protection) -> Result<()> {
+        let result = unsafe { libc::mprotect(self.ptr, self.len, prot.as_prot()) };
+        if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    /// Advises the kernel of the access pattern intended for the whole mapping.
+    pub fn advise(&self, advice: Advice) -> Result<()> {
+        self.advise_range(0, self.len(), advice)
+    }
+
+    /// Advises the kernel of the access pattern intended for the given byte range.
+    pub fn advise_range(&self, offset: usize, len: usize, advice: Advice) -> Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                   "memory map advise range is out of bounds"));
+        }
+
+        unsafe {
+            let ptr = (self.ptr as *const u8).offset((self.offset_adjustment + offset) as isize);
+            let alignment = ptr as usize % page_size();
+            let aligned_ptr = ptr.offset(-(alignment as isize));
+            let aligned_len = len + alignment;
+
+            let result = libc::madvise(aligned_ptr as *mut libc::c_void, aligned_len, advice.as_madvise());
+            if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+        }
+    }
+
+    /// Locks the full mapping into physical memory, preventing it from being paged to swap.
+    pub fn lock(&mut self) -> Result<()> {
+        let result = unsafe { libc::mlock(self.ptr, self.len) };
+        if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    /// Unlocks the full mapping, allowing it to be paged out again.
+    pub fn unlock(&mut self) -> Result<()> {
+        let result = unsafe { libc::munlock(self.ptr, self.len) };
+        if result == 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+}
+
+impl Deref for MmapInner {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe {
+            let ptr = (self.ptr as *const u8).offset(self.offset_adjustment as isize);
+            slice::from_raw_parts(ptr, self.len())
+        }
+    }
+}
+
+impl DerefMut for MmapInner {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            let ptr = (self.ptr as *mut u8).offset(self.offset_adjustment as isize);
+            slice::from_raw_parts_mut(ptr, self.len())
+        }
+    }
+}
+
+impl Drop for MmapInner {
+    fn drop(&mut self) {
+        let result = unsafe { libc::munmap(self.ptr, self.len) };
+        debug_assert_eq!(0, result, "unable to unmap memory map: {}", Error::last_os_error());
+    }
+}
+
+unsafe impl Sync for MmapInner {}
+unsafe impl Send for MmapInner {}
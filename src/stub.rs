@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+
+use {Advice, Protection};
+
+/// A stub `MmapInner` for targets with neither a `unix` nor a `windows` `cfg`, such as
+/// `wasm32-unknown-unknown`.
+///
+/// File-backed mappings are not supported on these targets and always fail; anonymous mappings
+/// are backed by a plain heap allocation, so downstream crates that only use anonymous maps (or
+/// merely name the types) stay compilable everywhere, with a runtime error only if a real
+/// file-backed mapping is attempted.
+pub struct MmapInner {
+    buf: Vec<u8>,
+}
+
+fn unsupported() -> Error {
+    Error::new(ErrorKind::Other, "memory mapping files is not supported on this target")
+}
+
+impl MmapInner {
+    pub fn open(_path: &Path, _offset: usize, _len: Option<usize>, _prot: Protection) -> Result<MmapInner> {
+        Err(unsupported())
+    }
+
+    pub fn from_file(_file: &File, _offset: usize, _len: Option<usize>, _prot: Protection) -> Result<MmapInner> {
+        Err(unsupported())
+    }
+
+    pub fn anonymous(len: usize, _prot: Protection) -> Result<MmapInner> {
+        if len == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map must have a non-zero length"));
+        }
+
+        Ok(MmapInner { buf: vec![0; len] })
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn flush_async(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn flush_range(&mut self, offset: usize, len: usize) -> Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map flush range is out of bounds"));
+        }
+
+        Ok(())
+    }
+
+    pub fn flush_async_range(&mut self, offset: usize, len: usize) -> Result<()> {
+        self.flush_range(offset, len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn mprotect(&mut self, _prot: Protection) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn advise(&self, _advice: Advice) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn advise_range(&self, offset: usize, len: usize, _advice: Advice) -> Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map advise range is out of bounds"));
+        }
+
+        Ok(())
+    }
+
+    pub fn lock(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn unlock(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Deref for MmapInner {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for MmapInner {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
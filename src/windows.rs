@@ -0,0 +1,309 @@
+#![allow(unsafe_code)]
+
+extern crate kernel32;
+extern crate winapi;
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::{Deref, DerefMut};
+use std::os::windows::io::AsRawHandle;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use {Advice, Protection};
+
+impl Advice {
+    /// Returns `true` if this advice has an effect on Windows (only `WillNeed` does, via
+    /// `PrefetchVirtualMemory`); the rest are accepted as no-ops.
+    fn is_prefetch(self) -> bool {
+        self == Advice::WillNeed
+    }
+}
+
+impl Protection {
+    fn as_page(self) -> winapi::DWORD {
+        match self {
+            Protection::Read => winapi::PAGE_READONLY,
+            Protection::ReadWrite => winapi::PAGE_READWRITE,
+            Protection::ReadCopy => winapi::PAGE_WRITECOPY,
+        }
+    }
+
+    fn as_access(self) -> winapi::DWORD {
+        match self {
+            Protection::Read => winapi::FILE_MAP_READ,
+            Protection::ReadWrite => winapi::FILE_MAP_WRITE,
+            Protection::ReadCopy => winapi::FILE_MAP_COPY,
+        }
+    }
+}
+
+/// Splits a 64-bit size or offset into the `(high, low)` `DWORD` pair that the
+/// `CreateFileMappingW`/`MapViewOfFile` APIs take, rather than silently truncating to the low 32
+/// bits.
+fn split_u64(value: u64) -> (winapi::DWORD, winapi::DWORD) {
+    ((value >> 32) as winapi::DWORD, (value & 0xFFFF_FFFF) as winapi::DWORD)
+}
+
+/// Returns the system's allocation granularity, the boundary that `MapViewOfFile` offsets must be
+/// aligned to.
+fn allocation_granularity() -> usize {
+    unsafe {
+        let mut info: winapi::SYSTEM_INFO = ::std::mem::zeroed();
+        kernel32::GetSystemInfo(&mut info);
+        info.dwAllocationGranularity as usize
+    }
+}
+
+pub struct MmapInner {
+    file: Option<File>,
+    mapping: winapi::HANDLE,
+    ptr: *mut winapi::c_void,
+    len: usize,
+    offset_adjustment: usize,
+}
+
+impl MmapInner {
+    /// Opens a file-backed memory map, starting `offset` bytes into the file and extending for
+    /// `len` bytes (or to the end of the file if `len` is `None`).
+    pub fn open(path: &Path, offset: usize, len: Option<usize>, prot: Protection) -> Result<MmapInner> {
+        let file = try!(prot.as_open_options().open(path));
+        MmapInner::from_file(&file, offset, len, prot)
+    }
+
+    /// Maps a region of an already-open file.
+    ///
+    /// A duplicate handle is stored alongside the mapping so that it can outlive the borrow and
+    /// be used to flush the mapping later. This works with files that have no path at all (e.g.
+    /// unlinked temp files or file handles received from elsewhere).
+    pub fn from_file(file: &File, offset: usize, len: Option<usize>, prot: Protection) -> Result<MmapInner> {
+        let file_len = try!(file.metadata()).len() as usize;
+
+        let len = match len {
+            Some(len) => len,
+            None => try!(file_len.checked_sub(offset).ok_or_else(|| {
+                Error::new(ErrorKind::InvalidInput, "memory map offset is out of bounds")
+            })),
+        };
+
+        if offset.checked_add(len).map_or(true, |end| end > file_len) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                   "memory map offset and length are out of bounds of the file"));
+        }
+
+        let duplicate = try!(file.try_clone());
+        unsafe { MmapInner::map(Some(duplicate), offset, len, prot) }
+    }
+
+    /// Opens an anonymous memory map.
+    pub fn anonymous(len: usize, prot: Protection) -> Result<MmapInner> {
+        unsafe { MmapInner::map(None, 0, len, prot) }
+    }
+
+    unsafe fn map(file: Option<File>, offset: usize, len: usize, prot: Protection) -> Result<MmapInner> {
+        let granularity = allocation_granularity();
+        let alignment = offset % granularity;
+        let aligned_offset = offset - alignment;
+        let aligned_len = len + alignment;
+
+        if aligned_len == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map must have a non-zero length"));
+        }
+
+        let handle = file.as_ref().map_or(winapi::INVALID_HANDLE_VALUE, |file| file.as_raw_handle());
+
+        // The mapping object must extend to the end of the view, not just cover its length.
+        let (size_high, size_low) = split_u64((aligned_offset + aligned_len) as u64);
+        let mapping = kernel32::CreateFileMappingW(handle,
+                                                     ptr::null_mut(),
+                                                     prot.as_page(),
+                                                     size_high,
+                                                     size_low,
+                                                     ptr::null());
+        if mapping.is_null() {
+            return Err(Error::last_os_error());
+        }
+
+        let (offset_high, offset_low) = split_u64(aligned_offset as u64);
+        let ptr = kernel32::MapViewOfFile(mapping,
+                                           prot.as_access(),
+                                           offset_high,
+                                           offset_low,
+                                           aligned_len as winapi::SIZE_T);
+
+        if ptr.is_null() {
+            let err = Error::last_os_error();
+            kernel32::CloseHandle(mapping);
+            return Err(err);
+        }
+
+        Ok(MmapInner {
+            file: file,
+            mapping: mapping,
+            ptr: ptr,
+            len: aligned_len,
+            offset_adjustment: alignment,
+        })
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        let result = unsafe { kernel32::FlushViewOfFile(self.ptr, self.len) };
+        if result == 0 {
+            return Err(Error::last_os_error());
+        }
+
+        if let Some(ref file) = self.file {
+            let result = unsafe { kernel32::FlushFileBuffers(file.as_raw_handle()) };
+            if result == 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn flush_async(&mut self) -> Result<()> {
+        let result = unsafe { kernel32::FlushViewOfFile(self.ptr, self.len) };
+        if result != 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    pub fn flush_range(&mut self, offset: usize, len: usize) -> Result<()> {
+        let (ptr, len) = try!(self.view_range(offset, len));
+
+        let result = unsafe { kernel32::FlushViewOfFile(ptr, len) };
+        if result == 0 {
+            return Err(Error::last_os_error());
+        }
+
+        if let Some(ref file) = self.file {
+            let result = unsafe { kernel32::FlushFileBuffers(file.as_raw_handle()) };
+            if result == 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn flush_async_range(&mut self, offset: usize, len: usize) -> Result<()> {
+        let (ptr, len) = try!(self.view_range(offset, len));
+
+        let result = unsafe { kernel32::FlushViewOfFile(ptr, len) };
+        if result != 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    /// Validates a logical `offset`/`len` and returns the page-aligned `(ptr, len)` pair that
+    /// covers it, suitable for passing to `FlushViewOfFile`.
+    fn view_range(&self, offset: usize, len: usize) -> Result<(winapi::LPCVOID, winapi::SIZE_T)> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "memory map flush range is out of bounds"));
+        }
+
+        unsafe {
+            let ptr = (self.ptr as *const u8).offset((self.offset_adjustment + offset) as isize);
+            let granularity = allocation_granularity();
+            let alignment = ptr as usize % granularity;
+            let aligned_ptr = ptr.offset(-(alignment as isize));
+            let aligned_len = len + alignment;
+
+            Ok((aligned_ptr as winapi::LPCVOID, aligned_len as winapi::SIZE_T))
+        }
+    }
+
+    /// Locks the full mapping into physical memory, preventing it from being paged to swap.
+    pub fn lock(&mut self) -> Result<()> {
+        let result = unsafe { kernel32::VirtualLock(self.ptr, self.len as winapi::SIZE_T) };
+        if result != 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    /// Unlocks the full mapping, allowing it to be paged out again.
+    pub fn unlock(&mut self) -> Result<()> {
+        let result = unsafe { kernel32::VirtualUnlock(self.ptr, self.len as winapi::SIZE_T) };
+        if result != 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len - self.offset_adjustment
+    }
+
+    /// Changes the protection of the full mapping in place.
+    pub fn mprotect(&mut self, prot: Protection) -> Result<()> {
+        let mut old_protect = 0;
+        let result = unsafe {
+            kernel32::VirtualProtect(self.ptr, self.len as winapi::SIZE_T, prot.as_page(), &mut old_protect)
+        };
+        if result != 0 { Ok(()) } else { Err(Error::last_os_error()) }
+    }
+
+    /// Advises the system of the access pattern intended for the whole mapping.
+    pub fn advise(&self, advice: Advice) -> Result<()> {
+        self.advise_range(0, self.len(), advice)
+    }
+
+    /// Advises the system of the access pattern intended for the given byte range.
+    ///
+    /// Only `Advice::WillNeed` has an effect on Windows; the rest are no-ops once the range has
+    /// been validated.
+    pub fn advise_range(&self, offset: usize, len: usize, advice: Advice) -> Result<()> {
+        if offset.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                   "memory map advise range is out of bounds"));
+        }
+
+        if !advice.is_prefetch() {
+            return Ok(());
+        }
+
+        unsafe {
+            let ptr = (self.ptr as *const u8).offset((self.offset_adjustment + offset) as isize);
+            let granularity = allocation_granularity();
+            let alignment = ptr as usize % granularity;
+            let aligned_ptr = ptr.offset(-(alignment as isize));
+            let aligned_len = len + alignment;
+
+            let mut entry = winapi::WIN32_MEMORY_RANGE_ENTRY {
+                VirtualAddress: aligned_ptr as winapi::PVOID,
+                NumberOfBytes: aligned_len as winapi::SIZE_T,
+            };
+
+            let result = kernel32::PrefetchVirtualMemory(kernel32::GetCurrentProcess(), 1, &mut entry, 0);
+            if result != 0 { Ok(()) } else { Err(Error::last_os_error()) }
+        }
+    }
+}
+
+impl Deref for MmapInner {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe {
+            let ptr = (self.ptr as *const u8).offset(self.offset_adjustment as isize);
+            slice::from_raw_parts(ptr, self.len())
+        }
+    }
+}
+
+impl DerefMut for MmapInner {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            let ptr = (self.ptr as *mut u8).offset(self.offset_adjustment as isize);
+            slice::from_raw_parts_mut(ptr, self.len())
+        }
+    }
+}
+
+impl Drop for MmapInner {
+    fn drop(&mut self) {
+        unsafe {
+            let base = self.ptr;
+            debug_assert!(kernel32::UnmapViewOfFile(base) != 0,
+                          "unable to unmap memory map: {}", Error::last_os_error());
+            debug_assert!(kernel32::CloseHandle(self.mapping) != 0,
+                          "unable to close memory map mapping handle: {}", Error::last_os_error());
+        }
+    }
+}
+
+unsafe impl Sync for MmapInner {}
+unsafe impl Send for MmapInner {}